@@ -1,43 +1,114 @@
+use std::collections::HashSet;
 use std::env;
 use std::fs::{self, File};
 use std::io::{self, Write, BufReader, Read};
-use std::path::PathBuf;
-use walkdir::{WalkDir, DirEntry};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use walkdir::WalkDir;
+use ignore::{WalkBuilder, WalkState};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use content_inspector::{inspect, ContentType};
+use serde::Serialize;
+
+/// 出力フォーマット
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// `<file path="...">...</file>` 形式（エスケープ済み）
+    ///
+    /// 旧来の `<relative/path>...</relative/path>`（パスをタグ名にする形）はやめている。
+    /// パスは任意の文字を含み得るため、タグ名としては本質的にエスケープ不可能で、
+    /// パスに `<` や `/` 等が含まれると壊れていた。そのため属性値として扱える
+    /// 固定タグ名の形に変更した。これは意図的な出力フォーマットの変更であり、
+    /// 旧形式の出力に依存するツールは読み替えが必要になる。
+    Xml,
+    /// パスを見出しとしたMarkdownのコードフェンス
+    Markdown,
+    /// `{ "path": ..., "content": ... }` の配列
+    Json,
+}
+
+/// 出力先
+enum OutputTarget {
+    File(PathBuf),
+    Stdout,
+}
+
+/// JSON出力時にシリアライズする1ファイル分のエントリ
+#[derive(Serialize)]
+struct FileEntry<'a> {
+    path: String,
+    content: &'a str,
+}
+
+/// コマンドライン引数を解釈した結果
+struct Config {
+    /// 処理対象として指定されたパス（ファイルまたはディレクトリ）
+    paths: Vec<PathBuf>,
+    /// `.gitignore` 等の無視ファイルを無視して全て走査するかどうか
+    no_ignore: bool,
+    /// ディレクトリ走査に使うスレッド数（0 の場合は環境に応じて自動で決定する。
+    /// `--no-ignore` 指定時の走査は単一スレッドで行うため使われない）
+    threads: usize,
+    /// `--include` で指定されたグロブ（1つでも指定されていれば、いずれかにマッチするファイルのみ対象）
+    includes: Option<GlobSet>,
+    /// `--exclude` で指定されたグロブ（マッチしたファイルは常に除外）
+    excludes: Option<GlobSet>,
+    /// `--ext` で指定された拡張子の許可リスト（未指定ならすべての拡張子を許可）
+    extensions: Option<HashSet<String>>,
+    /// シンボリックリンクを辿るかどうか（既定では辿らず、エントリごとスキップする）
+    follow_symlinks: bool,
+    /// 1ファイルあたりの読み込み上限バイト数（超過分は切り捨てて `<!-- truncated -->` を付与）
+    max_bytes: Option<u64>,
+    /// 出力フォーマット
+    format: OutputFormat,
+    /// 出力先（ファイルパス、または `-` で標準出力）
+    output: OutputTarget,
+}
 
 fn main() -> io::Result<()> {
     // コマンドライン引数の取得（プログラム名を除く）
     let args: Vec<String> = env::args().skip(1).collect();
 
     if args.is_empty() {
-        eprintln!("使い方: folder_to_text <対象ファイルまたはディレクトリのパス> [<対象ファイルまたはディレクトリのパス> ...]");
+        print_usage();
         std::process::exit(1);
     }
 
-    // 出力ファイルのパス
-    let output_file = "output.txt";
-    let mut output = File::create(output_file)?;
+    let config = parse_args(args);
 
-    // 各引数を処理
-    for arg in args {
-        let input_path = PathBuf::from(&arg);
+    if config.paths.is_empty() {
+        print_usage();
+        std::process::exit(1);
+    }
 
+    // 各引数を処理し、(相対パス, 内容) を集約する。フォーマットの組み立て（特にJSON）は
+    // 全件集まってから一括で行うため、出力そのものはここではまだ書き出さない
+    let mut entries: Vec<(PathBuf, String)> = Vec::new();
+
+    for input_path in &config.paths {
         if !input_path.exists() {
             eprintln!("指定されたパスは存在しません: {}", input_path.display());
             continue;
         }
 
         if input_path.is_dir() {
-            // ディレクトリの場合、再帰的に探索
-            if let Err(e) = process_directory(&input_path, &mut output) {
-                eprintln!("ディレクトリの処理中にエラーが発生しました: {} - {}", input_path.display(), e);
-                continue;
+            // ディレクトリの場合、再帰的に探索（出力順序は相対パスでソートして安定させる）
+            match process_directory(input_path, &config) {
+                Ok(mut blocks) => entries.append(&mut blocks),
+                Err(e) => {
+                    eprintln!("ディレクトリの処理中にエラーが発生しました: {} - {}", input_path.display(), e);
+                    continue;
+                }
             }
         } else if input_path.is_file() {
             // ファイルの場合、単独で処理
-            if let Err(e) = process_file(&input_path, &mut output) {
-                eprintln!("ファイルの処理中にエラーが発生しました: {} - {}", input_path.display(), e);
-                continue;
+            match render_file(input_path, &config) {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("ファイルの処理中にエラーが発生しました: {} - {}", input_path.display(), e);
+                    continue;
+                }
             }
         } else {
             eprintln!("指定されたパスはファイルでもディレクトリでもありません: {}", input_path.display());
@@ -45,42 +116,400 @@ fn main() -> io::Result<()> {
         }
     }
 
-    println!("テキストファイルの内容を '{}' に出力しました。", output_file);
+    // 複数の引数をまたいでも出力順序が安定するよう、最後にまとめてパスでソートする
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let formatted = format_entries(&entries, config.format);
+
+    match &config.output {
+        OutputTarget::File(path) => {
+            let mut output = File::create(path)?;
+            output.write_all(formatted.as_bytes())?;
+            eprintln!("{}個のファイルの内容を '{}' に出力しました。", entries.len(), path.display());
+        }
+        OutputTarget::Stdout => {
+            io::stdout().write_all(formatted.as_bytes())?;
+        }
+    }
+
     Ok(())
 }
 
-/// ディレクトリを再帰的に探索し、テキストファイルを処理する
-fn process_directory(dir: &PathBuf, output: &mut File) -> io::Result<()> {
-    // ディレクトリ内を再帰的に探索
-    for entry in WalkDir::new(dir)
-        .into_iter()
-        .filter_entry(|e| !is_excluded(e)) // 除外ディレクトリをフィルタ
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
+fn print_usage() {
+    eprintln!("使い方: folder_to_text [--no-ignore] [--threads N] [--include <glob>]... [--exclude <glob>]... [--ext ext1,ext2,...] [--follow-symlinks] [--max-bytes N] [--format xml|markdown|json] [--output <file>|-] <対象ファイルまたはディレクトリのパス> [<対象ファイルまたはディレクトリのパス> ...]");
+}
+
+/// コマンドライン引数をフラグとパスに振り分ける
+fn parse_args(args: Vec<String>) -> Config {
+    let mut paths = Vec::new();
+    let mut no_ignore = false;
+    let mut threads = 0;
+    let mut include_patterns = Vec::new();
+    let mut exclude_patterns = Vec::new();
+    let mut extensions: Option<HashSet<String>> = None;
+    let mut follow_symlinks = false;
+    let mut max_bytes = None;
+    let mut format = OutputFormat::Xml;
+    let mut output = OutputTarget::File(PathBuf::from("output.txt"));
 
-        // ディレクトリはスキップ
-        if path.is_dir() {
-            continue;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--no-ignore" => no_ignore = true,
+            "--follow-symlinks" => follow_symlinks = true,
+            "--format" => {
+                if let Some(value) = iter.next() {
+                    match value.as_str() {
+                        "xml" => format = OutputFormat::Xml,
+                        "markdown" => format = OutputFormat::Markdown,
+                        "json" => format = OutputFormat::Json,
+                        _ => eprintln!("不正な出力フォーマットです: {}（xml, markdown, json のいずれかを指定してください）", value),
+                    }
+                } else {
+                    eprintln!("--format にはフォーマット名を指定してください");
+                }
+            }
+            "--output" => {
+                if let Some(value) = iter.next() {
+                    output = if value == "-" {
+                        OutputTarget::Stdout
+                    } else {
+                        OutputTarget::File(PathBuf::from(value))
+                    };
+                } else {
+                    eprintln!("--output には出力先を指定してください");
+                }
+            }
+            "--threads" => {
+                if let Some(value) = iter.next() {
+                    match value.parse() {
+                        Ok(n) => threads = n,
+                        Err(_) => eprintln!("不正なスレッド数です: {}", value),
+                    }
+                } else {
+                    eprintln!("--threads にはスレッド数を指定してください");
+                }
+            }
+            "--max-bytes" => {
+                if let Some(value) = iter.next() {
+                    match value.parse() {
+                        Ok(n) => max_bytes = Some(n),
+                        Err(_) => eprintln!("不正な上限バイト数です: {}", value),
+                    }
+                } else {
+                    eprintln!("--max-bytes には上限バイト数を指定してください");
+                }
+            }
+            "--include" => {
+                if let Some(value) = iter.next() {
+                    include_patterns.push(value);
+                } else {
+                    eprintln!("--include にはグロブパターンを指定してください");
+                }
+            }
+            "--exclude" => {
+                if let Some(value) = iter.next() {
+                    exclude_patterns.push(value);
+                } else {
+                    eprintln!("--exclude にはグロブパターンを指定してください");
+                }
+            }
+            "--ext" => {
+                if let Some(value) = iter.next() {
+                    extensions
+                        .get_or_insert_with(HashSet::new)
+                        .extend(value.split(',').map(|s| s.trim().to_string()));
+                } else {
+                    eprintln!("--ext には拡張子を指定してください");
+                }
+            }
+            _ => paths.push(PathBuf::from(arg)),
         }
+    }
 
-        // ファイルを処理
-        if let Err(e) = process_file(&path.to_path_buf(), output) {
-            eprintln!("ファイルの処理中にエラーが発生しました: {} - {}", path.display(), e);
-            continue;
+    let includes = build_glob_set(&include_patterns);
+    let excludes = build_glob_set(&exclude_patterns);
+
+    Config { paths, no_ignore, threads, includes, excludes, extensions, follow_symlinks, max_bytes, format, output }
+}
+
+/// 集約済みの (相対パス, 内容) を指定フォーマットの文字列にまとめる
+fn format_entries(entries: &[(PathBuf, String)], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Xml => entries
+            .iter()
+            .map(|(path, content)| {
+                format!(
+                    "<file path=\"{}\">\n{}\n</file>\n\n",
+                    xml_escape(&path.display().to_string()),
+                    xml_escape(content)
+                )
+            })
+            .collect(),
+        OutputFormat::Markdown => entries
+            .iter()
+            .map(|(path, content)| {
+                let lang = markdown_lang(path);
+                format!("## {}\n\n```{}\n{}\n```\n\n", path.display(), lang, content)
+            })
+            .collect(),
+        OutputFormat::Json => {
+            let file_entries: Vec<FileEntry> = entries
+                .iter()
+                .map(|(path, content)| FileEntry {
+                    path: path.display().to_string(),
+                    content,
+                })
+                .collect();
+            serde_json::to_string_pretty(&file_entries).unwrap_or_default()
         }
     }
-    Ok(())
 }
 
-/// 単一のファイルを処理する
-fn process_file(file_path: &PathBuf, output: &mut File) -> io::Result<()> {
+/// XMLのテキスト/属性値として安全な形にエスケープする
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 拡張子からMarkdownのコードフェンス用言語ヒントを推定する（未知の拡張子は無指定）
+fn markdown_lang(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("rs") => "rust",
+        Some("toml") => "toml",
+        Some("md") => "markdown",
+        Some("json") => "json",
+        Some("js") => "javascript",
+        Some("ts") => "typescript",
+        Some("py") => "python",
+        Some("rb") => "ruby",
+        Some("go") => "go",
+        Some("java") => "java",
+        Some("c") => "c",
+        Some("h") => "c",
+        Some("cpp") | Some("cc") | Some("hpp") => "cpp",
+        Some("sh") => "bash",
+        Some("yaml") | Some("yml") => "yaml",
+        Some("html") => "html",
+        Some("css") => "css",
+        _ => "",
+    }
+}
+
+/// グロブパターンの一覧から `GlobSet` を構築する。パターンが空なら `None` を返す
+fn build_glob_set(patterns: &[String]) -> Option<GlobSet> {
+    if patterns.is_empty() {
+        return None;
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(e) => eprintln!("不正なグロブパターンです: {} - {}", pattern, e),
+        }
+    }
+
+    match builder.build() {
+        Ok(set) => Some(set),
+        Err(e) => {
+            eprintln!("グロブセットの構築に失敗しました: {}", e);
+            None
+        }
+    }
+}
+
+/// パスが `--include` / `--exclude` / `--ext` の条件を満たすかを判定する
+fn matches_filters(path: &Path, config: &Config) -> bool {
+    if let Some(includes) = &config.includes {
+        if !includes.is_match(path) {
+            return false;
+        }
+    }
+
+    if let Some(excludes) = &config.excludes {
+        if excludes.is_match(path) {
+            return false;
+        }
+    }
+
+    if let Some(extensions) = &config.extensions {
+        let matches_ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| extensions.contains(e));
+        if !matches_ext {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// ディレクトリを再帰的に探索し、テキストファイルの内容を (相対パス, 内容) の形で集める
+///
+/// `.gitignore` を尊重する場合は `ignore::WalkBuilder::build_parallel` で複数スレッドから
+/// 並行に読み取りつつ、結果を `Mutex` 越しに集約したうえでパスでソートすることで、
+/// スレッドの処理順に左右されない決定的な出力順序（パス順）にする。
+fn process_directory(dir: &Path, config: &Config) -> io::Result<Vec<(PathBuf, String)>> {
+    // シンボリックリンクの追跡先がこのルート配下に収まっているかを確認するための正規化済みパス
+    let root = fs::canonicalize(dir)?;
+    // 追跡済みのシンボリックリンク参照先（循環検出用）
+    let visited_links = Arc::new(Mutex::new(HashSet::new()));
+
+    if config.no_ignore {
+        // `--no-ignore` 指定時は従来どおりハードコードされた除外リストのみを適用する
+        let mut blocks = Vec::new();
+        for entry in WalkDir::new(dir)
+            .follow_links(config.follow_symlinks)
+            .into_iter()
+            .filter_entry(|e| !is_excluded(e.path()) && is_symlink_allowed(e.path(), e.path_is_symlink(), config, &root, &visited_links))
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+
+            // ディレクトリはスキップ
+            if path.is_dir() {
+                continue;
+            }
+
+            if let Some(entry) = render_file(path, config)? {
+                blocks.push(entry);
+            }
+        }
+        blocks.sort_by(|a, b| a.0.cmp(&b.0));
+        return Ok(blocks);
+    }
+
+    // `.gitignore` / `.ignore` / グローバルな git excludes を尊重して並列に探索する
+    let mut builder = WalkBuilder::new(dir);
+    builder.threads(config.threads);
+    // 既定では git リポジトリ外で `.gitignore` が適用されないため、リポジトリ外でも適用させる
+    builder.require_git(false);
+    // `WalkBuilder` は既定で隠しファイル・ディレクトリを無条件に除外するが、
+    // これは「無視ファイルを尊重する」という要件を超えた挙動になってしまう。
+    // `.env` や `.github/` のようにgit管理下で無視されていないドットファイルは
+    // 引き続き出力対象にする（gitignoreされている隠しファイルは引き続き除外される）
+    builder.hidden(false);
+    // ルート外参照や循環のチェックは自前（`is_symlink_allowed`）で行うため、
+    // 追跡自体の可否は `--follow-symlinks` にそのまま委ねる
+    builder.follow_links(config.follow_symlinks);
+    let walker = builder.build_parallel();
+
+    let blocks = Arc::new(Mutex::new(Vec::new()));
+    let root = Arc::new(root);
+    walker.run(|| {
+        let blocks = Arc::clone(&blocks);
+        let visited_links = Arc::clone(&visited_links);
+        let root = Arc::clone(&root);
+        Box::new(move |entry| {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    eprintln!("ディレクトリの探索中にエラーが発生しました: {}", e);
+                    return WalkState::Continue;
+                }
+            };
+            let path = entry.path();
+
+            // `hidden(false)` で隠しファイルの既定除外を解いている以上、`.git` 自体は
+            // 明示的に除外しないと中身（オブジェクトやhooksなど）まで出力されてしまう
+            if is_excluded(path) {
+                return WalkState::Skip;
+            }
+
+            let is_symlink = entry.path_is_symlink();
+
+            if !is_symlink_allowed(path, is_symlink, config, &root, &visited_links) {
+                // `Continue` だけではこのエントリを素通りするだけで、ディレクトリなら
+                // そのまま配下へ降りてしまう。拒否したシンボリックリンクの先は
+                // 降りずに打ち切るため `Skip` を返す
+                return WalkState::Skip;
+            }
+
+            if path.is_dir() {
+                return WalkState::Continue;
+            }
+
+            match render_file(path, config) {
+                Ok(Some(entry)) => blocks.lock().unwrap().push(entry),
+                Ok(None) => {}
+                Err(e) => eprintln!("ファイルの処理中にエラーが発生しました: {} - {}", path.display(), e),
+            }
+
+            WalkState::Continue
+        })
+    });
+
+    let mut blocks = Arc::try_unwrap(blocks)
+        .expect("ワーカースレッドはすべて終了しているはず")
+        .into_inner()
+        .unwrap();
+    blocks.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(blocks)
+}
+
+/// シンボリックリンクのエントリを処理してよいかどうかを判定する
+///
+/// `--follow-symlinks` が無効な場合はシンボリックリンクを一切処理しない。
+/// 有効な場合は参照先を正規化し、入力ルート配下に収まっているかを確認するとともに、
+/// 既に訪れた参照先であれば循環とみなしてスキップする。
+fn is_symlink_allowed(
+    path: &Path,
+    is_symlink: bool,
+    config: &Config,
+    root: &Path,
+    visited_links: &Mutex<HashSet<PathBuf>>,
+) -> bool {
+    if !is_symlink {
+        return true;
+    }
+
+    if !config.follow_symlinks {
+        return false;
+    }
+
+    let target = match fs::canonicalize(path) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("シンボリックリンクの参照先を解決できませんでした: {} - {}", path.display(), e);
+            return false;
+        }
+    };
+
+    if !target.starts_with(root) {
+        eprintln!("シンボリックリンクの参照先が対象ディレクトリの外にあるため無視します: {} -> {}", path.display(), target.display());
+        return false;
+    }
+
+    if !visited_links.lock().unwrap().insert(target.clone()) {
+        eprintln!("シンボリックリンクの循環を検出したため無視します: {} -> {}", path.display(), target.display());
+        return false;
+    }
+
+    true
+}
+
+/// ファイルを読み込み、テキストであれば (相対パス, 内容) の組を返す
+///
+/// 実際の出力フォーマットへの整形は `format_entries` が一括で行うため、ここではまだ
+/// XML/Markdown/JSON いずれのラッピングも行わない
+fn render_file(file_path: &Path, config: &Config) -> io::Result<Option<(PathBuf, String)>> {
+    // include/exclude/拡張子フィルタを満たさないファイルは内容の判定前に除外する
+    if !matches_filters(file_path, config) {
+        return Ok(None);
+    }
+
     // ファイルの読み込み
     let file = match File::open(file_path) {
         Ok(f) => f,
         Err(e) => {
             eprintln!("ファイルを開く際にエラーが発生しました: {} - {}", file_path.display(), e);
-            return Ok(()); // エラー発生時はスキップ
+            return Ok(None); // エラー発生時はスキップ
         }
     };
     let mut reader = BufReader::new(file);
@@ -89,61 +518,106 @@ fn process_file(file_path: &PathBuf, output: &mut File) -> io::Result<()> {
         Ok(n) => n,
         Err(e) => {
             eprintln!("ファイルを読み込む際にエラーが発生しました: {} - {}", file_path.display(), e);
-            return Ok(()); // エラー発生時はスキップ
+            return Ok(None); // エラー発生時はスキップ
         }
     };
 
     // コンテンツタイプの判定
     let content_type = inspect(&buffer[..n]);
 
-    // テキストファイルのみ処理
-    if is_text(content_type) {
-        // 相対パスを取得（プログラムの実行ディレクトリからの相対パス）
-        let relative_path = match file_path.strip_prefix(&env::current_dir()?) {
-            Ok(p) => p,
-            Err(_) => file_path.as_path(),
-        };
+    // テキストファイル以外は無視
+    if !is_text(content_type) {
+        return Ok(None);
+    }
 
-        // ファイル内容の読み込み
-        let content = match fs::read_to_string(file_path) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("ファイルを文字列として読み込む際にエラーが発生しました: {} - {}", file_path.display(), e);
-                return Ok(()); // エラー発生時はスキップ
-            }
-        };
+    // 相対パスを取得（プログラムの実行ディレクトリからの相対パス）
+    let relative_path = match file_path.strip_prefix(&env::current_dir()?) {
+        Ok(p) => p,
+        Err(_) => file_path,
+    };
 
-        // 出力ファイルに書き込む
-        if let Err(e) = writeln!(output, "<{}>", relative_path.display()) {
-            eprintln!("出力ファイルへの書き込みに失敗しました: {}", e);
-            return Ok(());
+    // ファイル内容をチャンク単位で読み込む（`--max-bytes` 指定時は上限を超える分をメモリへ載せない。
+    // 未指定の場合はファイル全体をメモリに保持する）
+    let (bytes, truncated) = match read_capped(file_path, config.max_bytes) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("ファイルを読み込む際にエラーが発生しました: {} - {}", file_path.display(), e);
+            return Ok(None); // エラー発生時はスキップ
         }
-        if let Err(e) = writeln!(output, "{}", content) {
-            eprintln!("出力ファイルへの書き込みに失敗しました: {}", e);
-            return Ok(());
+    };
+
+    // `is_text` が許可している文字コードに応じてデコードする。不正なバイト列は
+    // 1バイトの異常でファイル全体を捨てないよう、置換文字を挟んで読み進める
+    let mut content = match content_type {
+        ContentType::UTF_16LE => decode_utf16(&bytes, u16::from_le_bytes),
+        ContentType::UTF_16BE => decode_utf16(&bytes, u16::from_be_bytes),
+        _ => String::from_utf8_lossy(&bytes).into_owned(),
+    };
+
+    if truncated {
+        content.push_str("\n<!-- truncated -->");
+    }
+
+    Ok(Some((relative_path.to_path_buf(), content)))
+}
+
+/// ファイルをチャンク単位で読み込み、`max_bytes` が指定されていればその時点で打ち切る
+///
+/// 戻り値の真偽値は、上限に達して読み込みを打ち切った（＝切り詰めた）かどうかを示す
+fn read_capped(file_path: &Path, max_bytes: Option<u64>) -> io::Result<(Vec<u8>, bool)> {
+    let file = File::open(file_path)?;
+    let mut reader = BufReader::new(file);
+    let mut chunk = [0u8; 64 * 1024];
+    let mut data = Vec::new();
+    let mut truncated = false;
+
+    loop {
+        let n = reader.read(&mut chunk)?;
+        if n == 0 {
+            break;
         }
-        if let Err(e) = writeln!(output, "</{}>\n", relative_path.display()) {
-            eprintln!("出力ファイルへの書き込みに失敗しました: {}", e);
-            return Ok(());
+
+        if let Some(limit) = max_bytes {
+            let limit = limit as usize;
+            if data.len() >= limit {
+                truncated = true;
+                break;
+            }
+            let take = n.min(limit - data.len());
+            data.extend_from_slice(&chunk[..take]);
+            if take < n {
+                truncated = true;
+                break;
+            }
+        } else {
+            data.extend_from_slice(&chunk[..n]);
         }
     }
 
-    Ok(())
+    Ok((data, truncated))
+}
+
+/// UTF-16 (LE/BE) のバイト列を、不正な符号単位を置換文字に読み替えながら `String` に変換する
+fn decode_utf16(bytes: &[u8], to_u16: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| to_u16([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
 }
 
-/// エントリが除外ディレクトリ（.gitなど）でないかをチェック
-fn is_excluded(entry: &DirEntry) -> bool {
+/// パスが除外ディレクトリ（.gitなど）の配下でないかをチェック
+fn is_excluded(path: &Path) -> bool {
     // 除外したいディレクトリ名のリスト
     let excluded_dirs = [".git"];
 
-    entry
-        .path()
+    path
         .components()
         .any(|comp| {
             // `comp.as_os_str()` を `&str` に変換し、`excluded_dirs` に含まれているかを確認
             comp.as_os_str()
                 .to_str()
-                .map_or(false, |s| excluded_dirs.contains(&s))
+                .is_some_and(|s| excluded_dirs.contains(&s))
         })
 }
 